@@ -0,0 +1,375 @@
+// Binary graph/label format: varint-encoded payloads grouped into
+// fixed-size blocks, each individually compressed and checksummed.
+//
+// Layout:
+//   magic (4 bytes) "LPAB"
+//   version (u8)
+//   content (u8)
+//   codec (u8)
+//   nvertices (varint)
+//   nedges (varint, graphs only)
+//   block_size (varint, vertices per block)
+//   block*
+//
+// `content` distinguishes a graph file ([`write_graph`]) from a labels file
+// ([`write_labels`]); the two have structurally different block payloads, so
+// pointing [`read_graph`] at a labels file is rejected up
+// front instead of failing deep inside varint/weight parsing.
+//
+// Each block is: [compressed_len varint][uncompressed_len varint][checksum u64][compressed bytes]
+// `checksum` is the xxh3-64 of the compressed bytes and is verified before
+// decompression, so a corrupt block is rejected without ever running the
+// decompressor on garbage.
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+};
+
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{Edge, VertexId};
+
+const MAGIC: &[u8; 4] = b"LPAB";
+const VERSION: u8 = 1;
+const BLOCK_VERTICES: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Content {
+    Graph,
+    Labels,
+}
+
+impl Content {
+    fn tag(self) -> u8 {
+        match self {
+            Content::Graph => 0,
+            Content::Labels => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> io::Result<Self> {
+        match tag {
+            0 => Ok(Content::Graph),
+            1 => Ok(Content::Labels),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown binary LPA content tag",
+            )),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Content::Graph => "graph",
+            Content::Labels => "labels",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Codec {
+    Lz4,
+    Deflate,
+}
+
+impl Codec {
+    fn tag(self) -> u8 {
+        match self {
+            Codec::Lz4 => 0,
+            Codec::Deflate => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> io::Result<Self> {
+        match tag {
+            0 => Ok(Codec::Lz4),
+            1 => Ok(Codec::Deflate),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown block codec tag",
+            )),
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Codec::Lz4 => lz4_flex::compress(data),
+            Codec::Deflate => miniz_oxide::deflate::compress_to_vec(data, 6),
+        }
+    }
+
+    fn decompress(self, data: &[u8], uncompressed_len: usize) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::Lz4 => lz4_flex::decompress(data, uncompressed_len)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Codec::Deflate => miniz_oxide::inflate::decompress_to_vec(data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e))),
+        }
+    }
+}
+
+fn write_varint<W: Write>(w: &mut W, mut v: u64) -> io::Result<()> {
+    loop {
+        let mut byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v != 0 {
+            byte |= 0x80;
+        }
+        w.write_all(&[byte])?;
+        if v == 0 {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_varint<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut result = 0u64;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8; 1];
+        r.read_exact(&mut byte)?;
+        result |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    Ok(result)
+}
+
+fn write_block<W: Write>(w: &mut W, payload: &[u8], codec: Codec) -> io::Result<()> {
+    let compressed = codec.compress(payload);
+    let checksum = xxh3_64(&compressed);
+    write_varint(w, compressed.len() as u64)?;
+    write_varint(w, payload.len() as u64)?;
+    w.write_all(&checksum.to_le_bytes())?;
+    w.write_all(&compressed)
+}
+
+fn read_block<R: Read>(r: &mut R, codec: Codec) -> io::Result<Vec<u8>> {
+    let compressed_len = read_varint(r)? as usize;
+    let uncompressed_len = read_varint(r)? as usize;
+    let mut checksum_bytes = [0u8; 8];
+    r.read_exact(&mut checksum_bytes)?;
+    let expected_checksum = u64::from_le_bytes(checksum_bytes);
+
+    let mut compressed = vec![0u8; compressed_len];
+    r.read_exact(&mut compressed)?;
+
+    if xxh3_64(&compressed) != expected_checksum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "binary graph block failed checksum verification",
+        ));
+    }
+
+    codec.decompress(&compressed, uncompressed_len)
+}
+
+fn write_header<W: Write>(
+    w: &mut W,
+    content: Content,
+    codec: Codec,
+    nvertices: usize,
+) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION, content.tag(), codec.tag()])?;
+    write_varint(w, nvertices as u64)
+}
+
+fn read_header<R: Read>(r: &mut R, expected: Content) -> io::Result<(Codec, usize)> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a binary LPA file",
+        ));
+    }
+    let mut rest = [0u8; 3];
+    r.read_exact(&mut rest)?;
+    let [version, content_tag, codec_tag] = rest;
+    if version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported binary LPA version",
+        ));
+    }
+    let content = Content::from_tag(content_tag)?;
+    if content != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "expected a binary LPA {} file, found a {} file",
+                expected.label(),
+                content.label()
+            ),
+        ));
+    }
+    let codec = Codec::from_tag(codec_tag)?;
+    let nvertices = read_varint(r)? as usize;
+    Ok((codec, nvertices))
+}
+
+/// Writes the adjacency lists as delta-sorted, varint-encoded blocks.
+/// Each neighbor's edge weight follows its delta as a raw 8-byte float,
+/// since weights don't compress well as varints. Reachable from the CLI
+/// via `--save-graph`, independent of `--format` on the input side, so a
+/// CSV graph can be converted once and reloaded with `--format binary`.
+pub fn write_graph(
+    edges: &[Vec<Edge>],
+    nedges: usize,
+    file_path: String,
+    codec: Codec,
+) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(file_path)?);
+    write_header(&mut w, Content::Graph, codec, edges.len())?;
+    write_varint(&mut w, nedges as u64)?;
+    write_varint(&mut w, BLOCK_VERTICES as u64)?;
+
+    for chunk in edges.chunks(BLOCK_VERTICES) {
+        let mut payload = Vec::new();
+        for neighbors in chunk {
+            let mut sorted = neighbors.clone();
+            sorted.sort_unstable_by_key(|&(nbr, _)| nbr);
+            write_varint(&mut payload, sorted.len() as u64)?;
+            let mut prev = 0u64;
+            for &(nbr, weight) in &sorted {
+                write_varint(&mut payload, nbr as u64 - prev)?;
+                payload.extend_from_slice(&weight.to_le_bytes());
+                prev = nbr as u64;
+            }
+        }
+        write_block(&mut w, &payload, codec)?;
+    }
+
+    w.flush()
+}
+
+/// Reads back a graph written by [`write_graph`], decoding blocks one at a
+/// time as they are read and rejecting the file on the first checksum
+/// mismatch. All blocks are decoded before it returns.
+pub fn read_graph(file_path: String) -> io::Result<(Vec<VertexId>, Vec<Vec<Edge>>, usize)> {
+    let mut r = BufReader::new(File::open(file_path)?);
+    let (codec, nvertices) = read_header(&mut r, Content::Graph)?;
+    let nedges = read_varint(&mut r)? as usize;
+    let block_vertices = read_varint(&mut r)? as usize;
+
+    let vertices = (0..nvertices).collect::<Vec<_>>();
+    let mut edges: Vec<Vec<Edge>> = Vec::with_capacity(nvertices);
+
+    let mut remaining = nvertices;
+    while remaining > 0 {
+        let take = remaining.min(block_vertices);
+        let payload = read_block(&mut r, codec)?;
+        let mut cursor = &payload[..];
+        for _ in 0..take {
+            let len = read_varint(&mut cursor)? as usize;
+            let mut neighbors = Vec::with_capacity(len);
+            let mut prev = 0u64;
+            for _ in 0..len {
+                prev += read_varint(&mut cursor)?;
+                let mut weight_bytes = [0u8; 8];
+                cursor.read_exact(&mut weight_bytes)?;
+                neighbors.push((prev as VertexId, f64::from_le_bytes(weight_bytes)));
+            }
+            edges.push(neighbors);
+        }
+        remaining -= take;
+    }
+
+    Ok((vertices, edges, nedges))
+}
+
+/// Writes final vertex labels (the `store` output) using the same
+/// block/checksum pipeline as [`write_graph`].
+pub fn write_labels(vertices: &[VertexId], file_path: String, codec: Codec) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(file_path)?);
+    write_header(&mut w, Content::Labels, codec, vertices.len())?;
+    write_varint(&mut w, BLOCK_VERTICES as u64)?;
+
+    for chunk in vertices.chunks(BLOCK_VERTICES) {
+        let mut payload = Vec::new();
+        for &label in chunk {
+            write_varint(&mut payload, label as u64)?;
+        }
+        write_block(&mut w, &payload, codec)?;
+    }
+
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Seek;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("lpa-binformat-test-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn graph_round_trips_through_write_and_read() {
+        let edges: Vec<Vec<Edge>> = vec![
+            vec![(1, 2.0), (2, 1.5)],
+            vec![(0, 2.0)],
+            vec![(0, 1.5), (3, 1.0)],
+            vec![(2, 1.0)],
+        ];
+        let nedges = 3;
+        let path = temp_path("graph-roundtrip");
+
+        write_graph(&edges, nedges, path.clone(), Codec::Lz4).unwrap();
+        let (vertices, read_edges, read_nedges) = read_graph(path).unwrap();
+
+        assert_eq!(vertices, vec![0, 1, 2, 3]);
+        assert_eq!(read_nedges, nedges);
+        let mut expected = edges;
+        for neighbors in &mut expected {
+            neighbors.sort_unstable_by_key(|&(nbr, _)| nbr);
+        }
+        assert_eq!(read_edges, expected);
+    }
+
+    #[test]
+    fn corrupted_block_is_rejected_by_checksum() {
+        let edges: Vec<Vec<Edge>> = vec![vec![(1, 1.0)], vec![(0, 1.0)]];
+        let path = temp_path("graph-corruption");
+
+        write_graph(&edges, 1, path.clone(), Codec::Deflate).unwrap();
+
+        // Flip a byte inside the compressed payload, well past the header
+        // and block length/checksum fields.
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let corrupt_offset = file.metadata().unwrap().len() - 1;
+        file.seek(std::io::SeekFrom::Start(corrupt_offset)).unwrap();
+        let mut last_byte = [0u8; 1];
+        file.read_exact(&mut last_byte).unwrap();
+        file.seek(std::io::SeekFrom::Start(corrupt_offset)).unwrap();
+        file.write_all(&[last_byte[0] ^ 0xff]).unwrap();
+        drop(file);
+
+        let err = read_graph(path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("checksum"));
+    }
+
+    #[test]
+    fn reading_a_labels_file_as_a_graph_fails_with_a_clear_error() {
+        let path = temp_path("labels-as-graph");
+        write_labels(&[0, 1, 2], path.clone(), Codec::Lz4).unwrap();
+
+        let err = read_graph(path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("found a labels file"));
+    }
+}
@@ -0,0 +1,157 @@
+// Deterministic, coloring-based synchronous scheduling (`--schedule colored`).
+// A greedy graph coloring is computed once after load so that no two
+// adjacent vertices share a color; a full iteration then processes one
+// color class at a time. Within a class all updates are mutually
+// independent, so they run in parallel without the read/write interleaving
+// hazard of the default atomic schedule, and the whole iteration becomes
+// order-independent and reproducible given a fixed seed.
+use std::{collections::HashSet, time::Instant};
+
+use rand::{rngs::StdRng, SeedableRng};
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+
+use crate::{majority_label, statistics, Edge, VertexId};
+
+/// Assigns each vertex the smallest color not used by its already-colored
+/// neighbors, in vertex-id order, and groups vertices by color.
+pub fn greedy_coloring(edges: &[Vec<Edge>]) -> Vec<Vec<VertexId>> {
+    let mut colors: Vec<Option<usize>> = vec![None; edges.len()];
+    let mut max_color = 0;
+
+    for id in 0..edges.len() {
+        let used: HashSet<usize> = edges[id]
+            .iter()
+            .filter_map(|&(nbr, _)| colors[nbr])
+            .collect();
+
+        let mut color = 0;
+        while used.contains(&color) {
+            color += 1;
+        }
+        colors[id] = Some(color);
+        max_color = max_color.max(color);
+    }
+
+    let mut classes = vec![Vec::new(); max_color + 1];
+    for (id, color) in colors.into_iter().enumerate() {
+        classes[color.unwrap()].push(id);
+    }
+    classes
+}
+
+/// Derives a per-vertex, per-iteration RNG seed so tie-breaking is
+/// reproducible across runs of the same `--seed` regardless of scheduling.
+fn vertex_seed(seed: u64, id: VertexId, iteration: i64) -> u64 {
+    seed ^ (id as u64).wrapping_mul(0x9E3779B97F4A7C15)
+        ^ (iteration as u64).wrapping_mul(0xD1B54A32D192ED03)
+}
+
+pub fn lpa_colored(
+    vertices: &mut Vec<VertexId>,
+    edges: &Vec<Vec<Edge>>,
+    total_weight: f64,
+    limit: i64,
+    seed: u64,
+    color_classes: &[Vec<VertexId>],
+) -> (usize, f64) {
+    let (community, modularity) = statistics(vertices, edges, total_weight);
+    println!(
+        "INIT | community: {:?} modularity: {:?}",
+        community, modularity
+    );
+
+    let mut current = vertices.clone();
+
+    let mut iteration = 0;
+    let (mut best_community, mut best_modularity) = (0, -1.0);
+
+    while iteration < limit {
+        let now = Instant::now();
+        let mut changed = 0usize;
+
+        for class in color_classes {
+            let updates: Vec<(VertexId, VertexId)> = class
+                .par_iter()
+                .map_init(FxHashMap::default, |scratch, &id| {
+                    let mut rng = StdRng::seed_from_u64(vertex_seed(seed, id, iteration));
+                    let new_label = majority_label(
+                        &edges[id],
+                        |nbr| current[nbr],
+                        current[id],
+                        &mut rng,
+                        scratch,
+                    );
+                    (id, new_label)
+                })
+                .collect();
+
+            for (id, new_label) in updates {
+                if current[id] != new_label {
+                    changed += 1;
+                }
+                current[id] = new_label;
+            }
+        }
+
+        let (community, modularity) = statistics(&current, edges, total_weight);
+
+        if modularity > best_modularity {
+            *vertices = current.clone();
+            best_community = community;
+            best_modularity = modularity;
+        }
+
+        println!(
+            "iteration {:?} | changed: {:?} community: {:?} modularity: {:?} time: {:?}s",
+            iteration,
+            changed,
+            community,
+            modularity,
+            now.elapsed().as_secs() as f64 + now.elapsed().subsec_millis() as f64 * 1e-3
+        );
+        iteration += 1;
+
+        if changed == 0 {
+            break;
+        }
+    }
+
+    (best_community, best_modularity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn greedy_coloring_never_colors_adjacent_vertices_alike() {
+        // A 5-cycle plus one chord, so more than two colors are in play.
+        let edges: Vec<Vec<Edge>> = vec![
+            vec![(1, 1.0), (4, 1.0)],
+            vec![(0, 1.0), (2, 1.0)],
+            vec![(1, 1.0), (3, 1.0), (4, 1.0)],
+            vec![(2, 1.0), (4, 1.0)],
+            vec![(3, 1.0), (0, 1.0), (2, 1.0)],
+        ];
+
+        let classes = greedy_coloring(&edges);
+
+        let mut color_of = vec![0usize; edges.len()];
+        for (color, class) in classes.iter().enumerate() {
+            for &id in class {
+                color_of[id] = color;
+            }
+        }
+
+        for (id, neighbors) in edges.iter().enumerate() {
+            for &(nbr, _) in neighbors {
+                assert_ne!(
+                    color_of[id], color_of[nbr],
+                    "adjacent vertices {} and {} share color {}",
+                    id, nbr, color_of[id]
+                );
+            }
+        }
+    }
+}
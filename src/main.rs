@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashSet,
     sync::{
         atomic::{AtomicU64, Ordering},
         mpsc::sync_channel,
@@ -14,8 +14,19 @@ use clap::{ArgEnum, Clap};
 
 use csv::WriterBuilder;
 use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+
+mod binformat;
+mod color;
+mod report;
 
 type VertexId = usize;
+type Edge = (VertexId, f64);
+
+// at or below this degree, a linear scan over a SmallVec beats hashing into the
+// per-worker FxHashMap scratch buffer
+const SMALL_DEGREE_THRESHOLD: usize = 8;
 
 #[derive(ArgEnum)]
 enum Delimiter {
@@ -24,6 +35,33 @@ enum Delimiter {
     Comma,
 }
 
+#[derive(ArgEnum)]
+enum Format {
+    Csv,
+    Binary,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum Codec {
+    Lz4,
+    Deflate,
+}
+
+impl From<Codec> for binformat::Codec {
+    fn from(codec: Codec) -> Self {
+        match codec {
+            Codec::Lz4 => binformat::Codec::Lz4,
+            Codec::Deflate => binformat::Codec::Deflate,
+        }
+    }
+}
+
+#[derive(ArgEnum)]
+enum Schedule {
+    Atomic,
+    Colored,
+}
+
 #[derive(Clap)]
 #[clap()]
 struct Opts {
@@ -34,6 +72,31 @@ struct Opts {
     delimiter: Delimiter,
     #[clap(short, long, default_value = "20", about = "iteration limit")]
     limit: i64,
+    #[clap(arg_enum, long, default_value = "csv", about = "input/output file format")]
+    format: Format,
+    #[clap(arg_enum, long, default_value = "lz4", about = "binary block compression codec")]
+    codec: Codec,
+    #[clap(
+        long,
+        about = "write the loaded graph in the binary format to this path, for fast reload via --format binary"
+    )]
+    save_graph: Option<String>,
+    #[clap(long, about = "write a per-community report (JSON + CSV) to this path prefix")]
+    report: Option<String>,
+    #[clap(
+        long,
+        default_value = "1,10,100,1000,10000",
+        about = "ascending size histogram bucket lower-bounds, comma separated"
+    )]
+    report_buckets: String,
+    #[clap(arg_enum, long, default_value = "atomic", about = "update scheduling strategy")]
+    schedule: Schedule,
+    #[clap(
+        long,
+        default_value = "0",
+        about = "seed for per-vertex RNGs, used by --schedule colored for reproducible runs"
+    )]
+    seed: u64,
 }
 
 fn main() {
@@ -48,11 +111,32 @@ fn main() {
         Delimiter::Comma => ',',
     };
 
-    let (mut vertices, edges, nedges) = load(opts.csv_edge_path, delimiter);
+    let (mut vertices, edges, nedges) = match opts.format {
+        Format::Csv => load(opts.csv_edge_path, delimiter),
+        Format::Binary => binformat::read_graph(opts.csv_edge_path).unwrap(),
+    };
+    let total_weight = total_weight(&edges);
 
     println!("vertices: {:?}, edges: {:?}", vertices.len(), nedges);
 
-    let (best_community, best_modularity) = lpa(&mut vertices, &edges, nedges, opts.limit);
+    if let Some(file_path) = opts.save_graph {
+        binformat::write_graph(&edges, nedges, file_path, opts.codec.into()).unwrap();
+    }
+
+    let (best_community, best_modularity) = match opts.schedule {
+        Schedule::Atomic => lpa(&mut vertices, &edges, total_weight, opts.limit),
+        Schedule::Colored => {
+            let color_classes = color::greedy_coloring(&edges);
+            color::lpa_colored(
+                &mut vertices,
+                &edges,
+                total_weight,
+                opts.limit,
+                opts.seed,
+                &color_classes,
+            )
+        }
+    };
 
     println!(
         "best_community: {}, best_modularity: {}",
@@ -60,7 +144,23 @@ fn main() {
     );
 
     if let Some(file_path) = opts.output {
-        store(&vertices, file_path, delimiter);
+        match opts.format {
+            Format::Csv => store(&vertices, file_path, delimiter),
+            Format::Binary => binformat::write_labels(&vertices, file_path, opts.codec.into())
+                .unwrap(),
+        }
+    }
+
+    if let Some(prefix) = opts.report {
+        let buckets: Vec<usize> = opts
+            .report_buckets
+            .split(',')
+            .map(|bound| bound.trim().parse().unwrap())
+            .collect();
+        let communities = partition_communities(&vertices);
+        let summary = report::build(&communities, &edges, total_weight, &buckets);
+        report::write_json(&summary, &format!("{}.json", prefix));
+        report::write_csv(&summary, &format!("{}.csv", prefix));
     }
 
     println!(
@@ -69,7 +169,7 @@ fn main() {
     );
 }
 
-fn load(file_path: String, delimiter: char) -> (Vec<VertexId>, Vec<Vec<VertexId>>, usize) {
+fn load(file_path: String, delimiter: char) -> (Vec<VertexId>, Vec<Vec<Edge>>, usize) {
 	let now = Instant::now();
 
     let (sender, receiver) = sync_channel(1024);
@@ -78,32 +178,44 @@ fn load(file_path: String, delimiter: char) -> (Vec<VertexId>, Vec<Vec<VertexId>
         .has_headers(true)
         .delimiter(delimiter as _)
         .comment(Some(b'#'))
+        // the header row is 2 fields (nvertices, nedges) but data rows are
+        // 2 or 3 (the weight column is optional), so record width varies
+        .flexible(true)
         .from_path(file_path)
         .unwrap();
     let (nvertices, nedges) = rdr.headers().unwrap().clone().deserialize(None).unwrap();
 
     let vertices = (0..nvertices).collect::<Vec<_>>();
-    let mut edges: Vec<Vec<VertexId>> = vec![vec![]; nvertices as _];
-
-    thread::spawn(move || {
-        let mut records = rdr.deserialize();
-
-        while let Some(Ok((src, dst))) = records.next() {
-            sender.send((src, dst)).unwrap();
+    let mut edges: Vec<Vec<Edge>> = vec![vec![]; nvertices as _];
+
+    let parser = thread::spawn(move || {
+        // positional records rather than a fixed-arity deserialize, since the
+        // weight column is optional
+        for record in rdr.records() {
+            let record = record.expect("failed to parse CSV record");
+            let src: VertexId = record[0].parse().unwrap();
+            let dst: VertexId = record[1].parse().unwrap();
+            let weight: f64 = record.get(2).map_or(1.0, |w| w.parse().unwrap());
+            sender.send((src, dst, weight)).unwrap();
         }
     });
 
     let mut iter = receiver.iter();
-    while let Some((src, dst)) = iter.next() {
-        edges[src as usize].push(dst);
-        edges[dst as usize].push(src);
+    while let Some((src, dst, weight)) = iter.next() {
+        edges[src].push((dst, weight));
+        edges[dst].push((src, weight));
     }
+    parser.join().expect("CSV parsing thread panicked");
 
 	println!("load time: {:?}s", now.elapsed().as_secs() as f64 + now.elapsed().subsec_millis() as f64 * 1e-3);
 
     (vertices, edges, nedges)
 }
 
+fn total_weight(edges: &[Vec<Edge>]) -> f64 {
+    edges.iter().flatten().map(|&(_, weight)| weight).sum::<f64>() / 2.0
+}
+
 fn store(vertices: &[VertexId], file_path: String, delimiter: char) {
 	let now = Instant::now();
 
@@ -121,14 +233,14 @@ fn store(vertices: &[VertexId], file_path: String, delimiter: char) {
 
 fn lpa(
     vertices: &mut Vec<VertexId>,
-    edges: &Vec<Vec<VertexId>>,
-    nedges: usize,
+    edges: &Vec<Vec<Edge>>,
+    total_weight: f64,
     limit: i64,
 ) -> (usize, f64) {
     // naive random select function
     // let rand = || Instant::now().elapsed().as_nanos() & 1 == 1;
 
-    let (community, modularity) = statistics(vertices, &edges, nedges);
+    let (community, modularity) = statistics(vertices, &edges, total_weight);
     println!(
         "INIT | community: {:?} modularity: {:?}",
         community, modularity
@@ -148,52 +260,30 @@ fn lpa(
     while iteration < limit && active.load(Ordering::Relaxed) > 0 {
         let now = Instant::now();
         active.store(0, Ordering::Relaxed);
-        atomic_vertices
-            .par_iter()
-            .enumerate()
-            .for_each(|(id, label)| {
-                let mut rng = thread_rng();
-                let mut counter = 0;
-
-                let (mut new_label, mut max_count) = (label.load(Ordering::Relaxed), 0);
-                let mut label_counts: HashMap<VertexId, VertexId> = HashMap::new();
-                for &nbr in edges[id].iter() {
-                    let nbr_label = atomic_vertices[nbr as usize].load(Ordering::Acquire);
-                    let count = if let Some(count) = label_counts.get_mut(&(nbr_label as _)) {
-                        *count += 1;
-                        *count
-                    } else {
-                        label_counts.insert(nbr_label as _, 1);
-                        1
-                    };
-                    if count > max_count {
-                        max_count = count;
-                        new_label = nbr_label;
-                        counter = 1;
-                    } else if count == max_count {
-                        // reservoir sampling
-                        if rng.gen_ratio(1, counter + 1) {
-                            new_label = nbr_label;
-                        }
-                        counter += 1;
-                    }
-                    // if count > max_count || (count == max_count && rand()) {
-                    //     max_count = count;
-                    //     new_label = nbr_label;
-                    // }
-                }
+        atomic_vertices.par_iter().enumerate().for_each_init(
+            || (thread_rng(), FxHashMap::default()),
+            |(rng, scratch), (id, label)| {
+                let current_label = label.load(Ordering::Relaxed) as VertexId;
+                let new_label = majority_label(
+                    &edges[id],
+                    |nbr| atomic_vertices[nbr].load(Ordering::Acquire) as VertexId,
+                    current_label,
+                    rng,
+                    scratch,
+                ) as u64;
 
                 if label.swap(new_label, Ordering::Release) != new_label {
                     active.fetch_add(1, Ordering::Relaxed);
                 }
-            });
+            },
+        );
 
         let new_vertices = atomic_vertices
             .par_iter()
             .map(|x| x.load(Ordering::Relaxed) as VertexId)
             .collect();
 
-        let (community, modularity) = statistics(&new_vertices, &edges, nedges);
+        let (community, modularity) = statistics(&new_vertices, &edges, total_weight);
 
         if modularity > best_modularity {
             *vertices = new_vertices;
@@ -215,14 +305,87 @@ fn lpa(
     (best_community, best_modularity)
 }
 
-fn statistics(vertices: &Vec<VertexId>, edges: &Vec<Vec<VertexId>>, nedges: usize) -> (usize, f64) {
-    let mut communities = vec![HashSet::new(); vertices.len()];
-    let mut communities_count = HashSet::new();
+/// Picks the weighted-majority label among `edges`' current neighbor labels
+/// (as resolved by `label_of`), reservoir-sampling on ties. Low-degree
+/// vertices tally into a stack-allocated `SmallVec`; higher-degree vertices
+/// tally into the caller-owned `scratch` map, which is cleared and reused
+/// rather than reallocated per call.
+fn majority_label(
+    edges: &[Edge],
+    label_of: impl Fn(VertexId) -> VertexId,
+    current_label: VertexId,
+    rng: &mut impl Rng,
+    scratch: &mut FxHashMap<VertexId, f64>,
+) -> VertexId {
+    let mut counter = 0;
+    let (mut new_label, mut max_weight) = (current_label, 0.0);
+
+    if edges.len() <= SMALL_DEGREE_THRESHOLD {
+        let mut tally: SmallVec<[(VertexId, f64); SMALL_DEGREE_THRESHOLD]> = SmallVec::new();
+        for &(nbr, weight) in edges {
+            let nbr_label = label_of(nbr);
+            let weighted_sum = if let Some(entry) = tally.iter_mut().find(|(label, _)| *label == nbr_label) {
+                entry.1 += weight;
+                entry.1
+            } else {
+                tally.push((nbr_label, weight));
+                weight
+            };
+            if weighted_sum > max_weight {
+                max_weight = weighted_sum;
+                new_label = nbr_label;
+                counter = 1;
+            } else if weighted_sum == max_weight {
+                // reservoir sampling
+                if rng.gen_ratio(1, counter + 1) {
+                    new_label = nbr_label;
+                }
+                counter += 1;
+            }
+        }
+    } else {
+        scratch.clear();
+        for &(nbr, weight) in edges {
+            let nbr_label = label_of(nbr);
+            let weighted_sum = if let Some(sum) = scratch.get_mut(&nbr_label) {
+                *sum += weight;
+                *sum
+            } else {
+                scratch.insert(nbr_label, weight);
+                weight
+            };
+            if weighted_sum > max_weight {
+                max_weight = weighted_sum;
+                new_label = nbr_label;
+                counter = 1;
+            } else if weighted_sum == max_weight {
+                // reservoir sampling
+                if rng.gen_ratio(1, counter + 1) {
+                    new_label = nbr_label;
+                }
+                counter += 1;
+            }
+        }
+    }
+
+    new_label
+}
 
+fn partition_communities(vertices: &[VertexId]) -> Vec<HashSet<VertexId>> {
+    let mut communities = vec![HashSet::new(); vertices.len()];
     vertices.iter().enumerate().for_each(|(id, &label)| {
         communities[label].insert(id);
-        communities_count.insert(label);
     });
+    communities
+}
+
+fn statistics(
+    vertices: &Vec<VertexId>,
+    edges: &Vec<Vec<Edge>>,
+    total_weight: f64,
+) -> (usize, f64) {
+    let communities = partition_communities(vertices);
+    let communities_count = communities.iter().filter(|c| !c.is_empty()).count();
 
     let modularity = communities
         .par_iter()
@@ -230,20 +393,79 @@ fn statistics(vertices: &Vec<VertexId>, edges: &Vec<Vec<VertexId>>, nedges: usiz
             if community.len() == 0 {
                 return 0.0;
             }
-            let mut lv = 0;
-            let mut dv = 0;
+            let mut lv = 0.0;
+            let mut dv = 0.0;
             for &id in community {
-                for nbr in edges[id].iter() {
-                    if community.contains(nbr) {
-                        lv += 1;
+                for &(nbr, weight) in edges[id].iter() {
+                    if community.contains(&nbr) {
+                        lv += weight;
                     }
                 }
-                dv += edges[id].len();
+                dv += edges[id].iter().map(|&(_, weight)| weight).sum::<f64>();
             }
-            let m2 = (nedges * 2) as f64;
-            lv as f64 / m2 - (dv as f64 / m2) * (dv as f64 / m2)
+            let m2 = total_weight * 2.0;
+            lv / m2 - (dv / m2) * (dv / m2)
         })
         .sum::<f64>();
 
-    (communities_count.len(), modularity)
+    (communities_count, modularity)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("lpa-main-test-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn load_parses_rows_with_and_without_an_explicit_weight() {
+        let path = temp_path("load-weighted");
+        std::fs::write(&path, "3,2\n0,1,2.0\n1,2\n").unwrap();
+
+        let (vertices, edges, nedges) = load(path, ',');
+
+        assert_eq!(vertices, vec![0, 1, 2]);
+        assert_eq!(nedges, 2);
+        assert_eq!(edges[0], vec![(1, 2.0)]);
+        assert_eq!(edges[1], vec![(0, 2.0), (2, 1.0)]);
+        assert_eq!(edges[2], vec![(1, 1.0)]);
+    }
+
+    #[test]
+    fn majority_label_picks_the_highest_weighted_neighbor_label_below_threshold() {
+        let edges: Vec<Edge> = vec![(10, 1.0), (20, 2.0), (30, 2.0)];
+        let label_of = |nbr: VertexId| match nbr {
+            10 => 0,
+            20 | 30 => 1,
+            _ => unreachable!(),
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scratch = FxHashMap::default();
+
+        let new_label = majority_label(&edges, label_of, 0, &mut rng, &mut scratch);
+
+        assert_eq!(new_label, 1);
+    }
+
+    #[test]
+    fn majority_label_uses_the_scratch_map_above_the_small_degree_threshold() {
+        // one neighbor above SMALL_DEGREE_THRESHOLD forces the FxHashMap path
+        let edges: Vec<Edge> = (0..SMALL_DEGREE_THRESHOLD as VertexId + 1)
+            .map(|nbr| (nbr, 1.0))
+            .collect();
+        let label_of = |nbr: VertexId| if nbr < 3 { 7 } else { nbr };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scratch = FxHashMap::default();
+
+        let new_label = majority_label(&edges, label_of, 0, &mut rng, &mut scratch);
+
+        assert_eq!(new_label, 7);
+    }
 }
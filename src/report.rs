@@ -0,0 +1,216 @@
+// Post-run community report: a first pass computes per-community metrics
+// (size, internal weight, volume, conductance), a second pass folds those
+// into a size histogram and summary statistics. Emitted as JSON and CSV
+// behind `--report`.
+use std::{collections::HashSet, fs::File};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{Edge, VertexId};
+
+/// Intermediate per-community result computed in the first pass.
+#[derive(Serialize)]
+pub struct CommunityReport {
+    pub label: VertexId,
+    pub vertex_count: usize,
+    pub internal_weight: f64,
+    pub volume: f64,
+    pub conductance: f64,
+}
+
+#[derive(Serialize)]
+pub struct HistogramBucket {
+    pub lower: usize,
+    pub upper: Option<usize>,
+    pub count: usize,
+}
+
+/// Final summary folded from all [`CommunityReport`]s.
+#[derive(Serialize)]
+pub struct Summary {
+    pub community_count: usize,
+    pub largest_size: usize,
+    pub smallest_size: usize,
+    pub mean_size: f64,
+    pub median_size: f64,
+    pub histogram: Vec<HistogramBucket>,
+    pub communities: Vec<CommunityReport>,
+}
+
+/// Builds the report for a finished `lpa` run. `buckets` are the ascending
+/// lower bounds of the size histogram; the last bucket is open-ended.
+pub fn build(
+    communities: &[HashSet<VertexId>],
+    edges: &[Vec<Edge>],
+    total_weight: f64,
+    buckets: &[usize],
+) -> Summary {
+    let m2 = total_weight * 2.0;
+
+    let mut communities: Vec<CommunityReport> = communities
+        .par_iter()
+        .enumerate()
+        .filter(|(_, community)| !community.is_empty())
+        .map(|(label, community)| {
+            let mut internal_weight = 0.0;
+            let mut volume = 0.0;
+            for &id in community {
+                for &(nbr, weight) in edges[id].iter() {
+                    if community.contains(&nbr) {
+                        internal_weight += weight;
+                    }
+                }
+                volume += edges[id].iter().map(|&(_, weight)| weight).sum::<f64>();
+            }
+            let boundary = volume - internal_weight;
+            let conductance = if volume == 0.0 {
+                0.0
+            } else {
+                boundary / volume.min(m2 - volume)
+            };
+
+            CommunityReport {
+                label,
+                vertex_count: community.len(),
+                internal_weight,
+                volume,
+                conductance,
+            }
+        })
+        .collect();
+
+    communities.sort_unstable_by_key(|report| report.label);
+
+    let sizes: Vec<usize> = communities.iter().map(|report| report.vertex_count).collect();
+
+    Summary {
+        community_count: communities.len(),
+        largest_size: sizes.iter().copied().max().unwrap_or(0),
+        smallest_size: sizes.iter().copied().min().unwrap_or(0),
+        mean_size: mean(&sizes),
+        median_size: median(&sizes),
+        histogram: histogram(&sizes, buckets),
+        communities,
+    }
+}
+
+fn mean(sizes: &[usize]) -> f64 {
+    if sizes.is_empty() {
+        return 0.0;
+    }
+    sizes.iter().sum::<usize>() as f64 / sizes.len() as f64
+}
+
+fn median(sizes: &[usize]) -> f64 {
+    if sizes.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = sizes.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) as f64 / 2.0
+    } else {
+        sorted[mid] as f64
+    }
+}
+
+/// Buckets `buckets[i] <= size < buckets[i+1]`, with a final open-ended
+/// bucket for sizes at or above the last boundary. Sizes below the first
+/// boundary are counted in the first bucket.
+fn histogram(sizes: &[usize], buckets: &[usize]) -> Vec<HistogramBucket> {
+    let mut result: Vec<HistogramBucket> = buckets
+        .windows(2)
+        .map(|w| HistogramBucket {
+            lower: w[0],
+            upper: Some(w[1]),
+            count: 0,
+        })
+        .collect();
+    result.push(HistogramBucket {
+        lower: *buckets.last().unwrap_or(&0),
+        upper: None,
+        count: 0,
+    });
+
+    for &size in sizes {
+        let idx = buckets.iter().rposition(|&lower| size >= lower).unwrap_or(0);
+        result[idx].count += 1;
+    }
+
+    result
+}
+
+pub fn write_json(summary: &Summary, file_path: &str) {
+    let file = File::create(file_path).unwrap();
+    serde_json::to_writer_pretty(file, summary).unwrap();
+}
+
+pub fn write_csv(summary: &Summary, file_path: &str) {
+    let mut wtr = csv::Writer::from_path(file_path).unwrap();
+    wtr.write_record(["label", "vertex_count", "internal_weight", "volume", "conductance"])
+        .unwrap();
+    for report in &summary.communities {
+        wtr.write_record(&[
+            report.label.to_string(),
+            report.vertex_count.to_string(),
+            report.internal_weight.to_string(),
+            report.volume.to_string(),
+            report.conductance.to_string(),
+        ])
+        .unwrap();
+    }
+    wtr.flush().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two communities of 2 vertices each, joined by a single cross edge
+    // (1-2), so each community has internal_weight 2.0, volume 3.0, and a
+    // boundary of 1.0.
+    fn two_balanced_communities() -> (Vec<HashSet<VertexId>>, Vec<Vec<Edge>>, f64) {
+        let edges: Vec<Vec<Edge>> = vec![
+            vec![(1, 1.0)],
+            vec![(0, 1.0), (2, 1.0)],
+            vec![(1, 1.0), (3, 1.0)],
+            vec![(2, 1.0)],
+        ];
+        let communities = vec![
+            HashSet::from([0, 1]),
+            HashSet::from([2, 3]),
+        ];
+        (communities, edges, 3.0)
+    }
+
+    #[test]
+    fn build_computes_per_community_metrics() {
+        let (communities, edges, total_weight) = two_balanced_communities();
+
+        let summary = build(&communities, &edges, total_weight, &[1, 2, 5]);
+
+        assert_eq!(summary.community_count, 2);
+        assert_eq!(summary.largest_size, 2);
+        assert_eq!(summary.smallest_size, 2);
+        assert_eq!(summary.mean_size, 2.0);
+        assert_eq!(summary.median_size, 2.0);
+        for report in &summary.communities {
+            assert_eq!(report.vertex_count, 2);
+            assert_eq!(report.internal_weight, 2.0);
+            assert_eq!(report.volume, 3.0);
+            assert!((report.conductance - 1.0 / 3.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn build_buckets_sizes_into_the_matching_histogram_range() {
+        let (communities, edges, total_weight) = two_balanced_communities();
+
+        let summary = build(&communities, &edges, total_weight, &[1, 2, 5]);
+
+        let counts: Vec<usize> = summary.histogram.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, vec![0, 2, 0]);
+    }
+}